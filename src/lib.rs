@@ -0,0 +1,9 @@
+mod snowflake;
+
+pub use snowflake::{
+    AtomicSnowflakeGenerator, ClockBackwardsPolicy, Snowflake, SnowflakeBuilder,
+    SnowflakeGenerator, SnowflakeParts,
+};
+
+#[cfg(feature = "serde")]
+pub use snowflake::SnowflakeSettings;
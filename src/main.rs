@@ -1,6 +1,5 @@
 use std::sync::Arc;
 use std::thread;
-mod snowflake;
 use snowflake::SnowflakeGenerator;
 
 fn main() {
@@ -1,3 +1,4 @@
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -17,16 +18,219 @@ const TIMESTAMP_SHIFT: u64 = SEQUENCE_BITS + WORKER_ID_BITS + DATACENTER_ID_BITS
 const DATACENTER_ID_SHIFT: u64 = SEQUENCE_BITS + WORKER_ID_BITS;
 const WORKER_ID_SHIFT: u64 = SEQUENCE_BITS;
 
+/// Snowflake 的位布局与纪元配置
+///
+/// 默认布局对应 Twitter 经典的 5/5/12 划分，但通过 [`SnowflakeBuilder`] 可以
+/// 自定义各字段的位宽（三者之和必须小于 63，留出至少 1 位给时间戳）以及自定义
+/// 纪元，以适配例如 44 位时间戳 / 17 位序列号 / 2 位服务号 这类更高吞吐量的布局。
+#[derive(Debug, Clone, Copy)]
+pub struct SnowflakeConfig {
+    epoch: u64,
+    #[cfg(feature = "serde")]
+    worker_id_bits: u64,
+    #[cfg(feature = "serde")]
+    datacenter_id_bits: u64,
+    #[cfg(feature = "serde")]
+    sequence_bits: u64,
+    max_worker_id: u64,
+    max_datacenter_id: u64,
+    max_sequence: u64,
+    timestamp_shift: u64,
+    datacenter_id_shift: u64,
+    worker_id_shift: u64,
+}
+
+impl SnowflakeConfig {
+    fn new(
+        epoch: u64,
+        worker_id_bits: u64,
+        datacenter_id_bits: u64,
+        sequence_bits: u64,
+    ) -> Result<Self, &'static str> {
+        if worker_id_bits + datacenter_id_bits + sequence_bits >= 63 {
+            return Err(
+                "worker_id_bits + datacenter_id_bits + sequence_bits must leave room for the timestamp (sum must be less than 63)",
+            );
+        }
+
+        let worker_id_shift = sequence_bits;
+        let datacenter_id_shift = sequence_bits + worker_id_bits;
+        let timestamp_shift = sequence_bits + worker_id_bits + datacenter_id_bits;
+
+        Ok(Self {
+            epoch,
+            #[cfg(feature = "serde")]
+            worker_id_bits,
+            #[cfg(feature = "serde")]
+            datacenter_id_bits,
+            #[cfg(feature = "serde")]
+            sequence_bits,
+            max_worker_id: (1 << worker_id_bits) - 1,
+            max_datacenter_id: (1 << datacenter_id_bits) - 1,
+            max_sequence: (1 << sequence_bits) - 1,
+            timestamp_shift,
+            datacenter_id_shift,
+            worker_id_shift,
+        })
+    }
+
+    fn default_layout() -> Self {
+        Self::new(EPOCH, WORKER_ID_BITS, DATACENTER_ID_BITS, SEQUENCE_BITS)
+            .expect("default bit layout is always valid")
+    }
+}
+
+/// 用于自定义位布局与纪元的构建器
+///
+/// 默认等价于 Twitter 经典的 5/5/12 划分与 2021-01-01 纪元；调用
+/// `worker_id_bits`/`datacenter_id_bits`/`sequence_bits`/`epoch_millis` 等方法
+/// 可以覆盖其中任意一项。`build` 会校验三个位宽之和是否小于 63（需留出至少
+/// 1 位给时间戳）。
+pub struct SnowflakeBuilder {
+    worker_id: u64,
+    datacenter_id: u64,
+    epoch: u64,
+    worker_id_bits: u64,
+    datacenter_id_bits: u64,
+    sequence_bits: u64,
+    clock_backwards_policy: ClockBackwardsPolicy,
+}
+
+impl SnowflakeBuilder {
+    /// 创建一个使用默认位布局与默认纪元的构建器
+    pub fn new(worker_id: u64, datacenter_id: u64) -> Self {
+        Self {
+            worker_id,
+            datacenter_id,
+            epoch: EPOCH,
+            worker_id_bits: WORKER_ID_BITS,
+            datacenter_id_bits: DATACENTER_ID_BITS,
+            sequence_bits: SEQUENCE_BITS,
+            clock_backwards_policy: ClockBackwardsPolicy::default(),
+        }
+    }
+
+    /// 设置时钟回拨时的处理策略，默认拒绝（[`ClockBackwardsPolicy::Reject`]）
+    pub fn clock_backwards_policy(mut self, policy: ClockBackwardsPolicy) -> Self {
+        self.clock_backwards_policy = policy;
+        self
+    }
+
+    /// 设置 worker id 的位宽
+    pub fn worker_id_bits(mut self, bits: u64) -> Self {
+        self.worker_id_bits = bits;
+        self
+    }
+
+    /// 设置 datacenter id 的位宽
+    pub fn datacenter_id_bits(mut self, bits: u64) -> Self {
+        self.datacenter_id_bits = bits;
+        self
+    }
+
+    /// 设置序列号的位宽
+    pub fn sequence_bits(mut self, bits: u64) -> Self {
+        self.sequence_bits = bits;
+        self
+    }
+
+    /// 以自定义纪元（相对 Unix 纪元的毫秒数）覆盖默认纪元
+    pub fn epoch_millis(mut self, epoch: u64) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// 以 `SystemTime` 覆盖默认纪元
+    pub fn epoch(mut self, epoch: SystemTime) -> Self {
+        self.epoch = epoch
+            .duration_since(UNIX_EPOCH)
+            .expect("epoch must not be before the Unix epoch")
+            .as_millis() as u64;
+        self
+    }
+
+    /// 校验位布局并构建 [`Snowflake`]
+    pub fn build(self) -> Result<Snowflake, &'static str> {
+        let config = SnowflakeConfig::new(
+            self.epoch,
+            self.worker_id_bits,
+            self.datacenter_id_bits,
+            self.sequence_bits,
+        )?;
+
+        if self.worker_id > config.max_worker_id || self.datacenter_id > config.max_datacenter_id
+        {
+            return Err("Worker ID or Datacenter ID is out of range");
+        }
+
+        Ok(Snowflake {
+            last_timestamp: 0,
+            sequence: 0,
+            worker_id: self.worker_id,
+            datacenter_id: self.datacenter_id,
+            config,
+            clock_backwards_policy: self.clock_backwards_policy,
+        })
+    }
+}
+
+/// 生成器配置的可序列化快照（需要启用 `serde` feature）
+///
+/// 包含纪元、worker/datacenter id 与位布局，可用于把生成器配置持久化到配置
+/// 文件，并在下次启动时通过 [`SnowflakeSettings::into_builder`] 还原出等价的
+/// [`Snowflake`] 实例。
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct SnowflakeSettings {
+    pub worker_id: u64,
+    pub datacenter_id: u64,
+    pub epoch: u64,
+    pub worker_id_bits: u64,
+    pub datacenter_id_bits: u64,
+    pub sequence_bits: u64,
+}
+
+#[cfg(feature = "serde")]
+impl SnowflakeSettings {
+    /// 还原出一个与快照等价的 [`SnowflakeBuilder`]
+    pub fn into_builder(self) -> SnowflakeBuilder {
+        SnowflakeBuilder::new(self.worker_id, self.datacenter_id)
+            .worker_id_bits(self.worker_id_bits)
+            .datacenter_id_bits(self.datacenter_id_bits)
+            .sequence_bits(self.sequence_bits)
+            .epoch_millis(self.epoch)
+    }
+}
+
+/// 时钟回拨时的处理策略
+///
+/// 默认是 [`ClockBackwardsPolicy::Reject`]，与历史行为一致：只要观测到的时间戳
+/// 小于 `last_timestamp` 就直接报错。另外两种策略允许在小幅度的时钟回拨
+/// （例如 NTP 校时、虚拟机迁移）下继续提供服务，而不是让 ID 生成整体下线。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClockBackwardsPolicy {
+    /// 任何回拨都立即返回错误（默认行为）
+    #[default]
+    Reject,
+    /// 回拨在容忍范围内时，自旋等待系统时钟追上 `last_timestamp`；超出范围则报错
+    Wait { tolerance_millis: u64 },
+    /// 回拨在容忍范围内时，继续在 `last_timestamp` 上借用序列号生成 ID，
+    /// 直到系统时钟真正追上为止；超出范围则报错
+    BorrowSequence { tolerance_millis: u64 },
+}
+
 /// Snowflake 核心结构体
 pub struct Snowflake {
     last_timestamp: u64,
     sequence: u64,
     worker_id: u64,
     datacenter_id: u64,
+    config: SnowflakeConfig,
+    clock_backwards_policy: ClockBackwardsPolicy,
 }
 
 impl Snowflake {
-    /// 创建一个新的 Snowflake 实例
+    /// 创建一个使用默认位布局（Twitter 风格 5/5/12）的 Snowflake 实例
     pub fn new(worker_id: u64, datacenter_id: u64) -> Result<Self, &'static str> {
         if worker_id > MAX_WORKER_ID || datacenter_id > MAX_DATACENTER_ID {
             return Err("Worker ID or Datacenter ID is out of range");
@@ -36,50 +240,210 @@ impl Snowflake {
             sequence: 0,
             worker_id,
             datacenter_id,
+            config: SnowflakeConfig::default_layout(),
+            clock_backwards_policy: ClockBackwardsPolicy::default(),
         })
     }
 
     /// 生成下一个唯一 ID
     pub fn next_id(&mut self) -> Result<u64, &'static str> {
-        let mut timestamp = Self::current_timestamp();
+        let mut timestamp = self.resolve_timestamp()?;
 
-        if timestamp < self.last_timestamp {
-            return Err("Clock moved backwards. Refusing to generate id.");
+        if timestamp == self.last_timestamp {
+            self.sequence = (self.sequence + 1) & self.config.max_sequence;
+            if self.sequence == 0 {
+                timestamp = self.til_next_millis(self.last_timestamp);
+            }
+        } else {
+            self.sequence = 0;
+        }
+
+        self.last_timestamp = timestamp;
+
+        Ok(self.assemble_id(timestamp, self.sequence))
+    }
+
+    /// 一次性预留并生成 `n` 个连续的 ID
+    ///
+    /// 与逐个调用 `next_id` 不同，这里按“段”分配：在当前毫秒内一次性预留
+    /// `MAX_SEQUENCE - sequence + 1` 个序列号，用尽后再推进到下一毫秒继续
+    /// 分配，而不是重复走一遍单个 ID 的生成路径。这正是本方法相对于循环调用
+    /// `next_id` 节省同步开销的地方，也是它能与无锁版本的设计理念对应的原因。
+    pub fn next_ids(&mut self, n: usize) -> Result<Vec<u64>, &'static str> {
+        if n == 0 {
+            return Ok(Vec::new());
         }
 
+        let mut timestamp = self.resolve_timestamp()?;
         if timestamp == self.last_timestamp {
-            self.sequence = (self.sequence + 1) & MAX_SEQUENCE;
+            // 与同一毫秒内的上一次分配（无论来自 next_id 还是 next_ids）衔接，
+            // 先推进到下一个未用过的序列号，避免重复分配同一个 ID
+            self.sequence = (self.sequence + 1) & self.config.max_sequence;
             if self.sequence == 0 {
                 timestamp = self.til_next_millis(self.last_timestamp);
             }
         } else {
             self.sequence = 0;
         }
-
         self.last_timestamp = timestamp;
 
-        let id = ((timestamp - EPOCH) << TIMESTAMP_SHIFT)
-            | (self.datacenter_id << DATACENTER_ID_SHIFT)
-            | (self.worker_id << WORKER_ID_SHIFT)
-            | self.sequence;
+        let mut ids = Vec::with_capacity(n);
+        let mut remaining = n as u64;
+
+        loop {
+            let available = self.config.max_sequence - self.sequence + 1;
+            let take_now = available.min(remaining);
+
+            for offset in 0..take_now {
+                ids.push(self.assemble_id(self.last_timestamp, self.sequence + offset));
+            }
+
+            remaining -= take_now;
+            self.sequence += take_now;
+
+            if remaining == 0 {
+                // 回退到本段最后一个实际用掉的序列号，供后续调用续用
+                self.sequence -= 1;
+                break;
+            }
 
-        Ok(id)
+            // 当前毫秒的序列号已耗尽，自旋等待下一毫秒后继续分配剩余部分
+            timestamp = self.til_next_millis(self.last_timestamp);
+            self.last_timestamp = timestamp;
+            self.sequence = 0;
+        }
+
+        Ok(ids)
+    }
+
+    /// 处理时钟回拨策略后，返回本次应当使用的时间戳
+    fn resolve_timestamp(&mut self) -> Result<u64, &'static str> {
+        let timestamp = Self::current_timestamp();
+
+        if timestamp >= self.last_timestamp {
+            return Ok(timestamp);
+        }
+
+        let drift = self.last_timestamp - timestamp;
+        match self.clock_backwards_policy {
+            ClockBackwardsPolicy::Reject => {
+                Err("Clock moved backwards. Refusing to generate id.")
+            }
+            ClockBackwardsPolicy::Wait { tolerance_millis } => {
+                if drift > tolerance_millis {
+                    return Err("Clock moved backwards. Refusing to generate id.");
+                }
+                // 时钟回拨在容忍范围内，自旋等待直到追上 last_timestamp
+                Ok(wait_until(self.last_timestamp))
+            }
+            ClockBackwardsPolicy::BorrowSequence { tolerance_millis } => {
+                if drift > tolerance_millis {
+                    return Err("Clock moved backwards. Refusing to generate id.");
+                }
+                // 借用 last_timestamp 继续生成，直到真实时钟追上为止
+                Ok(self.last_timestamp)
+            }
+        }
+    }
+
+    fn assemble_id(&self, timestamp: u64, sequence: u64) -> u64 {
+        ((timestamp - self.config.epoch) << self.config.timestamp_shift)
+            | (self.datacenter_id << self.config.datacenter_id_shift)
+            | (self.worker_id << self.config.worker_id_shift)
+            | sequence
+    }
+
+    /// 生成下一个 ID 并以非负 `i64` 返回
+    ///
+    /// 63 位的 Snowflake 布局下符号位为 0；若生成的 ID 占用了符号位则返回错误，
+    /// 因此成功返回的结果总是非负的，
+    /// 便于与将 ID 当作有符号整数存储的数据库、JSON 等系统互操作。
+    pub fn next_id_i64(&mut self) -> Result<i64, &'static str> {
+        self.next_id()
+            .and_then(|id| i64::try_from(id).map_err(|_| "ID does not fit in a non-negative i64"))
+    }
+
+    /// 将一个已生成的 ID 还原为各个组成部分
+    ///
+    /// 解码时使用的是本实例的位布局与纪元配置，因此对 [`SnowflakeBuilder`]
+    /// 生成的自定义布局同样适用。
+    pub fn decode(&self, id: u64) -> SnowflakeParts {
+        let sequence = id & self.config.max_sequence;
+        let worker_id = (id >> self.config.worker_id_shift) & self.config.max_worker_id;
+        let datacenter_id = (id >> self.config.datacenter_id_shift) & self.config.max_datacenter_id;
+        let timestamp_millis = (id >> self.config.timestamp_shift) + self.config.epoch;
+
+        SnowflakeParts {
+            timestamp_millis,
+            datacenter_id,
+            worker_id,
+            sequence,
+        }
+    }
+
+    /// 导出当前实例的配置快照（需要启用 `serde` feature）
+    #[cfg(feature = "serde")]
+    pub fn settings(&self) -> SnowflakeSettings {
+        SnowflakeSettings {
+            worker_id: self.worker_id,
+            datacenter_id: self.datacenter_id,
+            epoch: self.config.epoch,
+            worker_id_bits: self.config.worker_id_bits,
+            datacenter_id_bits: self.config.datacenter_id_bits,
+            sequence_bits: self.config.sequence_bits,
+        }
     }
 
     fn current_timestamp() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_millis() as u64
+        current_timestamp()
     }
 
     fn til_next_millis(&self, last_timestamp: u64) -> u64 {
-        let mut timestamp = Self::current_timestamp();
-        while timestamp <= last_timestamp {
-            timestamp = Self::current_timestamp();
-        }
-        timestamp
+        til_next_millis(last_timestamp)
+    }
+}
+
+/// [`Snowflake::decode`] 返回的 ID 组成部分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnowflakeParts {
+    /// 生成该 ID 时的毫秒级 Unix 时间戳（已按纪元偏移修正）
+    pub timestamp_millis: u64,
+    pub datacenter_id: u64,
+    pub worker_id: u64,
+    pub sequence: u64,
+}
+
+impl SnowflakeParts {
+    /// 将毫秒时间戳重建为 `SystemTime`
+    pub fn system_time(&self) -> SystemTime {
+        UNIX_EPOCH + std::time::Duration::from_millis(self.timestamp_millis)
+    }
+}
+
+/// 获取当前毫秒时间戳
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis() as u64
+}
+
+/// 自旋等待，直到系统时钟推进到 `last_timestamp` 之后
+fn til_next_millis(last_timestamp: u64) -> u64 {
+    let mut timestamp = current_timestamp();
+    while timestamp <= last_timestamp {
+        timestamp = current_timestamp();
     }
+    timestamp
+}
+
+/// 自旋等待，直到系统时钟追上（大于等于）`target`
+fn wait_until(target: u64) -> u64 {
+    let mut timestamp = current_timestamp();
+    while timestamp < target {
+        timestamp = current_timestamp();
+    }
+    timestamp
 }
 
 /// 用于线程安全访问的包装器
@@ -95,10 +459,117 @@ impl SnowflakeGenerator {
         })
     }
 
+    /// 用一个（通常来自 [`SnowflakeBuilder`] 的）已配置 `Snowflake` 包装出线程安全的生成器
+    pub fn from_snowflake(snowflake: Snowflake) -> Self {
+        Self {
+            mutex: Mutex::new(snowflake),
+        }
+    }
+
     pub fn next_id(&self) -> Result<u64, &'static str> {
         // 加锁以保证线程安全
         self.mutex.lock().unwrap().next_id()
     }
+
+    /// 生成下一个 ID 并以非负 `i64` 返回，参见 [`Snowflake::next_id_i64`]
+    pub fn next_id_i64(&self) -> Result<i64, &'static str> {
+        self.mutex.lock().unwrap().next_id_i64()
+    }
+
+    /// 将一个已生成的 ID 还原为各个组成部分，参见 [`Snowflake::decode`]
+    pub fn decode(&self, id: u64) -> SnowflakeParts {
+        self.mutex.lock().unwrap().decode(id)
+    }
+
+    /// 导出当前实例的配置快照，参见 [`Snowflake::settings`]（需要启用 `serde` feature）
+    #[cfg(feature = "serde")]
+    pub fn settings(&self) -> SnowflakeSettings {
+        self.mutex.lock().unwrap().settings()
+    }
+
+    /// 一次性预留并生成 `n` 个 ID，参见 [`Snowflake::next_ids`]
+    ///
+    /// 只获取一次锁，内部按段分配序列号，这对批量写入、数据回填等需要
+    /// 一次性拿到大量 ID 的场景比逐个加锁调用 `next_id` 更高效。
+    pub fn next_ids(&self, n: usize) -> Result<Vec<u64>, &'static str> {
+        self.mutex.lock().unwrap().next_ids(n)
+    }
+
+    /// 返回一个 ID 迭代器：整批 ID 在一次加锁中按段预留完毕，再逐个交出
+    pub fn take(&self, n: usize) -> Box<dyn Iterator<Item = Result<u64, &'static str>>> {
+        match self.next_ids(n) {
+            Ok(ids) => Box::new(ids.into_iter().map(Ok)),
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        }
+    }
+}
+
+/// 无锁版本的 Snowflake 生成器
+///
+/// 与 [`SnowflakeGenerator`] 的互斥锁方案不同，本实现将 `last_timestamp` 和
+/// `sequence` 打包进单个 `AtomicU64`，通过 `compare_exchange_weak` 的 CAS 循环
+/// 推进状态，完全避免了锁竞争。位布局固定为 [`Snowflake::new`] 的默认布局
+/// （Twitter 风格 5/5/12 划分与默认纪元），不支持 [`SnowflakeBuilder`] 的自定义配置。
+pub struct AtomicSnowflakeGenerator {
+    // 高位存储 last_timestamp，低 SEQUENCE_BITS 位存储 sequence
+    state: AtomicU64,
+    worker_id: u64,
+    datacenter_id: u64,
+}
+
+impl AtomicSnowflakeGenerator {
+    /// 创建一个新的无锁 Snowflake 生成器
+    pub fn new(worker_id: u64, datacenter_id: u64) -> Result<Self, &'static str> {
+        if worker_id > MAX_WORKER_ID || datacenter_id > MAX_DATACENTER_ID {
+            return Err("Worker ID or Datacenter ID is out of range");
+        }
+        Ok(Self {
+            state: AtomicU64::new(0),
+            worker_id,
+            datacenter_id,
+        })
+    }
+
+    /// 生成下一个唯一 ID，整个过程不持有任何锁
+    pub fn next_id(&self) -> Result<u64, &'static str> {
+        loop {
+            let current = self.state.load(Ordering::Acquire);
+            let last_timestamp = current >> SEQUENCE_BITS;
+            let last_sequence = current & MAX_SEQUENCE;
+
+            let mut timestamp = current_timestamp();
+
+            if timestamp < last_timestamp {
+                return Err("Clock moved backwards. Refusing to generate id.");
+            }
+
+            let sequence = if timestamp == last_timestamp {
+                let next_sequence = (last_sequence + 1) & MAX_SEQUENCE;
+                if next_sequence == 0 {
+                    // 当前毫秒的序列号已耗尽，自旋等待下一毫秒
+                    timestamp = til_next_millis(last_timestamp);
+                }
+                next_sequence
+            } else {
+                0
+            };
+
+            let new_state = (timestamp << SEQUENCE_BITS) | sequence;
+
+            if self
+                .state
+                .compare_exchange_weak(current, new_state, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                let id = ((timestamp - EPOCH) << TIMESTAMP_SHIFT)
+                    | (self.datacenter_id << DATACENTER_ID_SHIFT)
+                    | (self.worker_id << WORKER_ID_SHIFT)
+                    | sequence;
+                return Ok(id);
+            }
+            // CAS 失败，说明有其他线程抢先更新了状态，重试
+        }
+    }
 }
 
 #[cfg(test)]
@@ -180,4 +651,347 @@ mod tests {
         }
         assert_eq!(all_ids.len(), num_threads * ids_per_thread);
     }
+
+    #[test]
+    fn test_atomic_generator_concurrent_uniqueness() {
+        let generator = Arc::new(AtomicSnowflakeGenerator::new(1, 1).unwrap());
+        let mut handles = vec![];
+        let num_threads = 10;
+        let ids_per_thread = 1000;
+
+        for _ in 0..num_threads {
+            let gen_clone = Arc::clone(&generator);
+            let handle = thread::spawn(move || {
+                let mut thread_ids = Vec::new();
+                for _ in 0..ids_per_thread {
+                    thread_ids.push(gen_clone.next_id().unwrap());
+                }
+                thread_ids
+            });
+            handles.push(handle);
+        }
+
+        let mut all_ids = HashSet::new();
+        for handle in handles {
+            let thread_ids = handle.join().unwrap();
+            for id in thread_ids {
+                assert!(all_ids.insert(id), "无锁生成器并发生成时出现重复 ID: {}", id);
+            }
+        }
+        assert_eq!(all_ids.len(), num_threads * ids_per_thread);
+    }
+
+    #[test]
+    fn test_builder_default_matches_twitter_layout() {
+        let mut snowflake = SnowflakeBuilder::new(5, 10).build().unwrap();
+        let id = snowflake.next_id().unwrap();
+
+        let decoded_worker_id = (id >> WORKER_ID_SHIFT) & MAX_WORKER_ID;
+        let decoded_datacenter_id = (id >> DATACENTER_ID_SHIFT) & MAX_DATACENTER_ID;
+        assert_eq!(decoded_worker_id, 5);
+        assert_eq!(decoded_datacenter_id, 10);
+    }
+
+    #[test]
+    fn test_builder_rejects_bit_widths_leaving_no_room_for_timestamp() {
+        let result = SnowflakeBuilder::new(0, 0)
+            .worker_id_bits(31)
+            .datacenter_id_bits(31)
+            .sequence_bits(1)
+            .build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_builder_custom_layout_and_epoch() {
+        // 44 位时间戳 / 17 位序列号 / 2 位服务号
+        let mut snowflake = SnowflakeBuilder::new(2, 0)
+            .worker_id_bits(2)
+            .datacenter_id_bits(0)
+            .sequence_bits(17)
+            .epoch_millis(0)
+            .build()
+            .unwrap();
+
+        let id = snowflake.next_id().unwrap();
+        assert_eq!(id & 0x1FFFF, 0);
+        assert_eq!((id >> 17) & 0b11, 2);
+    }
+
+    #[test]
+    fn test_builder_epoch_from_system_time() {
+        let custom_epoch = SystemTime::now() - std::time::Duration::from_secs(1);
+        let expected_epoch_millis = custom_epoch
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+
+        let mut snowflake = SnowflakeBuilder::new(1, 1).epoch(custom_epoch).build().unwrap();
+        let id = snowflake.next_id().unwrap();
+
+        // 纪元设在“刚过去 1 秒”，原始时间戳字段应当只比 1000 毫秒略多
+        let raw_timestamp_bits = id >> TIMESTAMP_SHIFT;
+        assert!(raw_timestamp_bits < 2000);
+
+        let parts = snowflake.decode(id);
+        assert_eq!(parts.timestamp_millis, expected_epoch_millis + raw_timestamp_bits);
+
+        let current_ts = Snowflake::current_timestamp();
+        assert!(parts.timestamp_millis <= current_ts && current_ts - parts.timestamp_millis < 50);
+    }
+
+    #[test]
+    fn test_decode_roundtrips_next_id() {
+        let worker_id = 5;
+        let datacenter_id = 10;
+        let mut snowflake = Snowflake::new(worker_id, datacenter_id).unwrap();
+        let id = snowflake.next_id().unwrap();
+
+        let parts = snowflake.decode(id);
+        assert_eq!(parts.worker_id, worker_id);
+        assert_eq!(parts.datacenter_id, datacenter_id);
+        assert_eq!(parts.sequence, 0);
+
+        let current_ts = Snowflake::current_timestamp();
+        assert!(parts.timestamp_millis <= current_ts && current_ts - parts.timestamp_millis < 50);
+    }
+
+    #[test]
+    fn test_decode_respects_custom_layout() {
+        let mut snowflake = SnowflakeBuilder::new(2, 0)
+            .worker_id_bits(2)
+            .datacenter_id_bits(0)
+            .sequence_bits(17)
+            .epoch_millis(0)
+            .build()
+            .unwrap();
+
+        let id = snowflake.next_id().unwrap();
+        let parts = snowflake.decode(id);
+        assert_eq!(parts.worker_id, 2);
+        assert_eq!(parts.datacenter_id, 0);
+        assert_eq!(parts.sequence, 0);
+    }
+
+    #[test]
+    fn test_decode_parts_system_time_roundtrips_to_now() {
+        let mut snowflake = Snowflake::new(1, 1).unwrap();
+        let id = snowflake.next_id().unwrap();
+        let parts = snowflake.decode(id);
+
+        let system_time = parts.system_time();
+        let reconstructed_millis = system_time
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        assert_eq!(reconstructed_millis, parts.timestamp_millis);
+
+        let now = SystemTime::now();
+        let drift = now
+            .duration_since(system_time)
+            .unwrap_or(std::time::Duration::from_millis(0));
+        assert!(drift.as_millis() < 50);
+    }
+
+    #[test]
+    fn test_clock_backwards_default_policy_rejects() {
+        let mut snowflake = Snowflake::new(1, 1).unwrap();
+        // 模拟时钟回拨：将 last_timestamp 人为设置到未来
+        snowflake.last_timestamp = Snowflake::current_timestamp() + 1000;
+        assert!(snowflake.next_id().is_err());
+    }
+
+    #[test]
+    fn test_clock_backwards_wait_within_tolerance_succeeds() {
+        let mut snowflake = SnowflakeBuilder::new(1, 1)
+            .clock_backwards_policy(ClockBackwardsPolicy::Wait {
+                tolerance_millis: 1000,
+            })
+            .build()
+            .unwrap();
+        // 回拨幅度在容忍范围内，应当自旋等待后成功返回
+        snowflake.last_timestamp = Snowflake::current_timestamp() + 5;
+        assert!(snowflake.next_id().is_ok());
+    }
+
+    #[test]
+    fn test_clock_backwards_wait_beyond_tolerance_errors() {
+        let mut snowflake = SnowflakeBuilder::new(1, 1)
+            .clock_backwards_policy(ClockBackwardsPolicy::Wait {
+                tolerance_millis: 5,
+            })
+            .build()
+            .unwrap();
+        snowflake.last_timestamp = Snowflake::current_timestamp() + 1000;
+        assert!(snowflake.next_id().is_err());
+    }
+
+    #[test]
+    fn test_clock_backwards_borrow_sequence_continues_on_last_timestamp() {
+        let mut snowflake = SnowflakeBuilder::new(1, 1)
+            .clock_backwards_policy(ClockBackwardsPolicy::BorrowSequence {
+                tolerance_millis: 1000,
+            })
+            .build()
+            .unwrap();
+        let future_timestamp = Snowflake::current_timestamp() + 50;
+        snowflake.last_timestamp = future_timestamp;
+        snowflake.sequence = 7;
+
+        let id = snowflake.next_id().unwrap();
+        let parts = snowflake.decode(id);
+        assert_eq!(parts.timestamp_millis, future_timestamp);
+        assert_eq!(parts.sequence, 8);
+    }
+
+    #[test]
+    fn test_next_ids_returns_unique_batch() {
+        let generator = SnowflakeGenerator::new(1, 1).unwrap();
+        let ids = generator.next_ids(5000).unwrap();
+
+        assert_eq!(ids.len(), 5000);
+        let unique: HashSet<_> = ids.iter().copied().collect();
+        assert_eq!(unique.len(), 5000);
+    }
+
+    #[test]
+    fn test_next_ids_reserves_a_contiguous_segment_within_one_millisecond() {
+        // 序列号只有 4 位（0..=15），一次请求 10 个必然全部落在同一毫秒段内
+        let mut snowflake = SnowflakeBuilder::new(1, 1)
+            .worker_id_bits(5)
+            .datacenter_id_bits(5)
+            .sequence_bits(4)
+            .build()
+            .unwrap();
+
+        let ids = snowflake.next_ids(10).unwrap();
+        let sequences: Vec<u64> = ids.iter().map(|&id| snowflake.decode(id).sequence).collect();
+        assert_eq!(sequences, (0..10).collect::<Vec<_>>());
+
+        let timestamps: HashSet<u64> = ids
+            .iter()
+            .map(|&id| snowflake.decode(id).timestamp_millis)
+            .collect();
+        assert_eq!(timestamps.len(), 1, "段内分配不应跨越多个毫秒");
+    }
+
+    #[test]
+    fn test_next_ids_rolls_into_next_millisecond_when_segment_is_exhausted() {
+        // 序列号只有 2 位（最多 4 个/毫秒），请求 6 个必然跨越毫秒边界
+        let mut snowflake = SnowflakeBuilder::new(1, 1)
+            .worker_id_bits(5)
+            .datacenter_id_bits(5)
+            .sequence_bits(2)
+            .build()
+            .unwrap();
+
+        let ids = snowflake.next_ids(6).unwrap();
+        assert_eq!(ids.len(), 6);
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 6);
+
+        let timestamps: Vec<u64> = ids
+            .iter()
+            .map(|&id| snowflake.decode(id).timestamp_millis)
+            .collect();
+        assert!(timestamps.windows(2).all(|w| w[0] <= w[1]), "时间戳必须单调不减");
+        assert!(
+            timestamps.last().unwrap() > timestamps.first().unwrap(),
+            "应当跨越至少一个毫秒边界"
+        );
+    }
+
+    #[test]
+    fn test_next_id_then_next_ids_in_same_millisecond_do_not_overlap() {
+        // 借用同一（未来的）毫秒，确保两次调用必然落在同一毫秒内
+        let mut snowflake = SnowflakeBuilder::new(1, 1)
+            .clock_backwards_policy(ClockBackwardsPolicy::BorrowSequence {
+                tolerance_millis: 1000,
+            })
+            .build()
+            .unwrap();
+        let borrowed_timestamp = Snowflake::current_timestamp() + 50;
+        snowflake.last_timestamp = borrowed_timestamp;
+        snowflake.sequence = 0;
+
+        let first = snowflake.next_id().unwrap();
+        let batch = snowflake.next_ids(5).unwrap();
+
+        assert!(
+            !batch.contains(&first),
+            "next_ids 不应重复 next_id 刚刚分配出的 ID"
+        );
+        let mut all = batch.clone();
+        all.push(first);
+        assert_eq!(
+            all.iter().collect::<HashSet<_>>().len(),
+            all.len(),
+            "同一毫秒内衔接分配的 ID 必须全部唯一"
+        );
+    }
+
+    #[test]
+    fn test_back_to_back_next_ids_in_same_millisecond_do_not_overlap() {
+        // 借用同一（未来的）毫秒，确保两次 next_ids 调用必然落在同一毫秒内
+        let mut snowflake = SnowflakeBuilder::new(1, 1)
+            .clock_backwards_policy(ClockBackwardsPolicy::BorrowSequence {
+                tolerance_millis: 1000,
+            })
+            .build()
+            .unwrap();
+        let borrowed_timestamp = Snowflake::current_timestamp() + 50;
+        snowflake.last_timestamp = borrowed_timestamp;
+        snowflake.sequence = 0;
+
+        let first_batch = snowflake.next_ids(3).unwrap();
+        let second_batch = snowflake.next_ids(3).unwrap();
+
+        let mut all = first_batch.clone();
+        all.extend(second_batch.clone());
+        assert_eq!(
+            all.iter().collect::<HashSet<_>>().len(),
+            all.len(),
+            "两次 next_ids 衔接分配的 ID 必须全部唯一，不能有重叠"
+        );
+    }
+
+    #[test]
+    fn test_take_iterator_yields_unique_ids() {
+        let generator = SnowflakeGenerator::new(1, 1).unwrap();
+        let ids: Vec<u64> = generator.take(1000).collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(ids.len(), 1000);
+        let unique: HashSet<_> = ids.iter().copied().collect();
+        assert_eq!(unique.len(), 1000);
+    }
+
+    #[test]
+    fn test_next_id_i64_is_always_non_negative() {
+        let mut snowflake = Snowflake::new(MAX_WORKER_ID, MAX_DATACENTER_ID).unwrap();
+        for _ in 0..1000 {
+            assert!(snowflake.next_id_i64().unwrap() >= 0);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_settings_roundtrip_through_builder() {
+        let mut original = SnowflakeBuilder::new(5, 10)
+            .worker_id_bits(6)
+            .datacenter_id_bits(6)
+            .sequence_bits(10)
+            .epoch_millis(1_600_000_000_000)
+            .build()
+            .unwrap();
+
+        let settings = original.settings();
+        let json = serde_json::to_string(&settings).unwrap();
+        let restored: SnowflakeSettings = serde_json::from_str(&json).unwrap();
+
+        let rebuilt = restored.into_builder().build().unwrap();
+
+        let id = original.next_id().unwrap();
+        let parts = rebuilt.decode(id);
+        assert_eq!(parts.worker_id, 5);
+        assert_eq!(parts.datacenter_id, 10);
+    }
 }
\ No newline at end of file